@@ -1,15 +1,48 @@
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
 
 use clap::{Parser, Subcommand};
+use flate2::read::GzDecoder;
+use flate2::Compression;
 use indicatif::{ProgressBar, ProgressStyle};
-use rayon::prelude::*;
-use tar::{Builder, Header, Archive};
+use tar::{Builder, EntryType, Header, Archive};
 use walkdir::WalkDir;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
 use zstd::stream::Encoder;
 
+/// Archive container format, picked from the input/output file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    TarZst,
+    TarGz,
+    TarXz,
+    Zip,
+}
+
+impl Format {
+    fn from_path(path: &str) -> Format {
+        let lower = path.to_lowercase();
+        if lower.ends_with(".zip") {
+            Format::Zip
+        } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Format::TarGz
+        } else if lower.ends_with(".tar.xz") || lower.ends_with(".txz") {
+            Format::TarXz
+        } else {
+            // Falls back to zstd, matching the tool's original default.
+            Format::TarZst
+        }
+    }
+}
+
 #[derive(Parser)]
 struct Cli {
     #[command(subcommand)]
@@ -21,43 +54,86 @@ enum Commands {
     Compress {
         input_folder: String,
         output_file: String,
+        /// Sort entries deterministically and zero out per-file mtime/uid/gid so that
+        /// compressing the same folder twice yields byte-identical output.
+        #[arg(long)]
+        reproducible: bool,
+        /// Accepted only for .tar.zst output, whose content-defined chunking keeps
+        /// unchanged chunks as identical tar members across revisions; other formats
+        /// are rejected. It does not change how the archive is written: the outer zstd
+        /// stream has no rsyncable window resets and its dictionary is retrained from
+        /// the input on every run, so a small edit can change compressed bytes
+        /// throughout the archive.
+        #[arg(long)]
+        rsyncable: bool,
     },
     Decompress {
         input_file: String,
         output_folder: String,
     },
-}
-
-struct FileData {
-    rel_path: PathBuf,
-    compressed: Vec<u8>,
-    header: Header,
-    success: bool,
+    List {
+        input_file: String,
+    },
 }
 
 fn main() -> io::Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Compress { input_folder, output_file } => {
-            compress_folder(&input_folder, &output_file)?
+        Commands::Compress { input_folder, output_file, reproducible, rsyncable } => {
+            compress_folder(&input_folder, &output_file, reproducible, rsyncable)?
         }
         Commands::Decompress { input_file, output_folder } => {
             decompress_folder(&input_file, &output_folder)?
         }
+        Commands::List { input_file } => {
+            list_archive(&input_file)?
+        }
     }
 
     Ok(())
 }
 
-fn compress_folder(input_folder: &str, output_file: &str) -> io::Result<()> {
-    let entries: Vec<_> = WalkDir::new(input_folder)
+fn compress_folder(
+    input_folder: &str,
+    output_file: &str,
+    reproducible: bool,
+    rsyncable: bool,
+) -> io::Result<()> {
+    let format = Format::from_path(output_file);
+
+    if format == Format::Zip {
+        if reproducible || rsyncable {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--reproducible and --rsyncable apply to the tar-based outputs (.tar.zst/.tar.gz/.tar.xz); .zip output goes through a separate writer that doesn't honor either",
+            ));
+        }
+        return compress_folder_zip(input_folder, output_file);
+    }
+
+    if rsyncable && format != Format::TarZst {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--rsyncable relies on the content-defined chunking used for .tar.zst output; .tar.gz/.tar.xz store whole-file blobs with no such alignment",
+        ));
+    }
+
+    let mut entries: Vec<_> = WalkDir::new(input_folder)
+        .min_depth(1)
         .into_iter()
         .filter_map(Result::ok)
-        .filter(|e| e.file_type().is_file())
         .collect();
 
-    let total_size: u64 = entries.iter().map(|e| e.metadata().unwrap().len()).sum();
+    if reproducible {
+        entries.sort_by(|a, b| a.path().cmp(b.path()));
+    }
+
+    let total_size: u64 = entries
+        .iter()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.metadata().unwrap().len())
+        .sum();
 
     let global_pb = ProgressBar::new(total_size);
     global_pb.set_style(
@@ -68,112 +144,686 @@ fn compress_folder(input_folder: &str, output_file: &str) -> io::Result<()> {
         .progress_chars("█▉▊▋▌▍▎▏ "),
     );
 
-    let results = Arc::new(Mutex::new(Vec::<FileData>::new()));
+    // Priming the single outer encoder with a dictionary (trained from a sample of the
+    // input files) benefits small/similar files without needing an independent
+    // compression context per file or chunk, so it composes cleanly with the streaming,
+    // compress-once design below.
+    let dict = if format == Format::TarZst {
+        train_dictionary(&entries)
+    } else {
+        None
+    };
+
+    // Files are streamed straight into the tar builder and compressed exactly once by
+    // the single outer encoder below, so memory use stays flat regardless of file size.
+    let output = File::create(output_file)?;
+    let writer = make_encoder(output, format, reproducible, dict.as_deref())?;
+    let mut tar = Builder::new(writer);
 
-    entries.par_iter().for_each(|entry| {
+    // Cross-file content-defined dedup for TarZst (see `chunk_stream`): a file's content
+    // is never buffered whole, only up to one chunk (`CHUNK_MAX_SIZE` bytes) at a time,
+    // and a chunk already emitted for an earlier file is never written again.
+    let mut chunk_seen: HashSet<ChunkId> = HashSet::new();
+
+    let mut results: Vec<(PathBuf, bool)> = Vec::with_capacity(entries.len());
+
+    for entry in &entries {
         let path = entry.path().to_path_buf();
         let rel_path = path.strip_prefix(input_folder).unwrap().to_path_buf();
-        let file_size = entry.metadata().unwrap().len();
-
-        let mut file = match File::open(&path) {
-            Ok(f) => f,
-            Err(_) => {
-                results.lock().unwrap().push(FileData {
-                    rel_path,
-                    compressed: vec![],
-                    header: Header::new_gnu(),
-                    success: false,
-                });
-                return;
+
+        let success = append_entry(
+            &mut tar,
+            &path,
+            &rel_path,
+            reproducible,
+            &global_pb,
+            format,
+            &mut chunk_seen,
+        )
+        .is_ok();
+        results.push((rel_path, success));
+    }
+
+    global_pb.finish_with_message("📦 Сжатие завершено");
+
+    println!("\n📃 Результаты:");
+    for (rel_path, success) in &results {
+        println!(
+            "{:<60} [{}]",
+            rel_path.display(),
+            if *success { "OK" } else { "ERR" }
+        );
+    }
+
+    println!("\n✅ Папка '{}' сжата в '{}'", input_folder, output_file);
+    Ok(())
+}
+
+/// Build the header for `path` and append it (and its content, for regular files) to
+/// `tar` in one pass, reading through `pb` so the progress bar advances as bytes are
+/// actually streamed rather than after a full read into memory. For `Format::TarZst`,
+/// regular files are split into content-defined chunks and deduplicated against
+/// `chunk_seen` instead of being stored whole (see `chunk_stream`).
+fn append_entry<W: Write>(
+    tar: &mut Builder<W>,
+    path: &Path,
+    rel_path: &Path,
+    reproducible: bool,
+    pb: &ProgressBar,
+    format: Format,
+    chunk_seen: &mut HashSet<ChunkId>,
+) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+
+    let mut header = Header::new_gnu();
+    header.set_mode(metadata.mode());
+    if reproducible {
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+    } else {
+        header.set_mtime(metadata.mtime() as u64);
+        header.set_uid(metadata.uid() as u64);
+        header.set_gid(metadata.gid() as u64);
+    }
+
+    let file_type = metadata.file_type();
+
+    if file_type.is_symlink() {
+        let target = fs::read_link(path)?;
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        header.set_path(rel_path)?;
+        header.set_link_name(&target)?;
+        header.set_cksum();
+        return tar.append(&header, io::empty());
+    }
+
+    if file_type.is_dir() {
+        header.set_entry_type(EntryType::Directory);
+        header.set_size(0);
+        header.set_path(rel_path)?;
+        header.set_cksum();
+        return tar.append(&header, io::empty());
+    }
+
+    if file_type.is_fifo()
+        || file_type.is_char_device()
+        || file_type.is_block_device()
+        || file_type.is_socket()
+    {
+        eprintln!(
+            "⚠️  Пропуск '{}': специальные файлы (FIFO/устройства/сокеты) не поддерживаются",
+            rel_path.display()
+        );
+        return Ok(());
+    }
+
+    header.set_entry_type(EntryType::Regular);
+
+    if format == Format::TarZst {
+        let file = File::open(path)?;
+        let mut manifest = Vec::new();
+
+        chunk_stream(BufReader::new(ProgressRead { inner: file, pb }), |bytes| {
+            let id: ChunkId = *blake3::hash(bytes).as_bytes();
+            manifest.extend_from_slice(&id);
+
+            if chunk_seen.insert(id) {
+                let mut chunk_header = Header::new_gnu();
+                chunk_header.set_mode(0o644);
+                chunk_header.set_mtime(0);
+                chunk_header.set_uid(0);
+                chunk_header.set_gid(0);
+                chunk_header.set_entry_type(EntryType::Regular);
+                chunk_header.set_size(bytes.len() as u64);
+                chunk_header.set_path(chunk_entry_path(&id))?;
+                chunk_header.set_cksum();
+                tar.append(&chunk_header, bytes)?;
             }
-        };
 
-        let mut buffer = Vec::with_capacity(file_size as usize);
-        let mut chunk = [0u8; 8192];
-        loop {
-            let n = match file.read(&mut chunk) {
-                Ok(0) => break,
-                Ok(n) => n,
-                Err(_) => {
-                    results.lock().unwrap().push(FileData {
-                        rel_path,
-                        compressed: vec![],
-                        header: Header::new_gnu(),
-                        success: false,
-                    });
-                    return;
-                }
+            Ok(())
+        })?;
+
+        header.set_size(manifest.len() as u64);
+        header.set_path(rel_path)?;
+        header.set_cksum();
+        return tar.append(&header, &manifest[..]);
+    }
+
+    header.set_size(metadata.len());
+    header.set_path(rel_path)?;
+    header.set_cksum();
+
+    let file = File::open(path)?;
+    tar.append_data(&mut header, rel_path, ProgressRead { inner: file, pb })
+}
+
+/// Wraps a `Read` so every chunk pulled out of it advances the progress bar, without
+/// buffering the data anywhere along the way.
+struct ProgressRead<'a, R> {
+    inner: R,
+    pb: &'a ProgressBar,
+}
+
+impl<'a, R: Read> Read for ProgressRead<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pb.inc(n as u64);
+        Ok(n)
+    }
+}
+
+/// A content id: the blake3 digest of a chunk's bytes.
+type ChunkId = [u8; 32];
+
+/// Tar-internal directory that holds each unique chunk exactly once, addressed by id.
+const CHUNKS_DIR: &str = ".chunks";
+const CHUNK_MIN_SIZE: usize = 2 * 1024;
+const CHUNK_MAX_SIZE: usize = 64 * 1024;
+/// Chosen so `hash & CHUNK_MASK == 0` fires roughly every 8 KiB on random data.
+const CHUNK_MASK: u64 = 8 * 1024 - 1;
+
+/// Splitmix64-seeded table for the Gear rolling hash used by `chunk_stream`.
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+    while i < 256 {
+        seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        table[i] = z;
+        i += 1;
+    }
+    table
+}
+
+static GEAR: [u64; 256] = gear_table();
+
+/// Splits `reader`'s byte stream into content-defined chunks using a rolling Gear hash
+/// and hands each chunk's bytes to `on_chunk` as soon as a boundary is found. At most one
+/// chunk (`CHUNK_MAX_SIZE` bytes) is ever held in memory, regardless of the file's size.
+fn chunk_stream<R: Read>(
+    mut reader: R,
+    mut on_chunk: impl FnMut(&[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut chunk = Vec::with_capacity(CHUNK_MIN_SIZE);
+    let mut hash: u64 = 0;
+    let mut byte = [0u8; 1];
+
+    loop {
+        if reader.read(&mut byte)? == 0 {
+            break;
+        }
+        chunk.push(byte[0]);
+        hash = (hash << 1).wrapping_add(GEAR[byte[0] as usize]);
+
+        let boundary = chunk.len() >= CHUNK_MAX_SIZE
+            || (chunk.len() >= CHUNK_MIN_SIZE && hash & CHUNK_MASK == 0);
+
+        if boundary {
+            on_chunk(&chunk)?;
+            chunk.clear();
+            hash = 0;
+        }
+    }
+
+    if !chunk.is_empty() {
+        on_chunk(&chunk)?;
+    }
+
+    Ok(())
+}
+
+fn to_hex(id: &ChunkId) -> String {
+    id.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn chunk_entry_path(id: &ChunkId) -> String {
+    format!("{}/{}", CHUNKS_DIR, to_hex(id))
+}
+
+fn parse_chunk_id(hex_id: &str) -> io::Result<ChunkId> {
+    if hex_id.len() != 64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed chunk id"));
+    }
+
+    let mut id = ChunkId::default();
+    for (i, byte_hex) in hex_id.as_bytes().chunks(2).enumerate() {
+        let byte_str = std::str::from_utf8(byte_hex)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "malformed chunk id"))?;
+        id[i] = u8::from_str_radix(byte_str, 16)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "malformed chunk id"))?;
+    }
+    Ok(id)
+}
+
+const DICT_SIZE: usize = 110 * 1024;
+const DICT_MIN_SAMPLES: usize = 8;
+/// Upper bound on how many files are read for dictionary training, so that a folder
+/// with huge numbers of small files doesn't turn the training pass into a full second
+/// read of the whole tree.
+const DICT_MAX_SAMPLES: usize = 2048;
+/// Only this many leading bytes of each file are read as a training sample, so the
+/// training pass never holds a large file in memory.
+const DICT_SAMPLE_MAX_BYTES: u64 = 16 * 1024;
+
+/// Reads the leading bytes of the first `DICT_MAX_SAMPLES` regular input files and trains
+/// a zstd dictionary from them, so the single outer encoder can be primed with patterns
+/// common to small/similar files.
+/// Returns `None` if there aren't enough usable samples to train one.
+fn train_dictionary(entries: &[walkdir::DirEntry]) -> Option<Vec<u8>> {
+    let mut samples = Vec::new();
+
+    for entry in entries {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        let read = File::open(entry.path())
+            .and_then(|f| f.take(DICT_SAMPLE_MAX_BYTES).read_to_end(&mut bytes));
+        if read.is_ok() && !bytes.is_empty() {
+            samples.push(bytes);
+        }
+        if samples.len() >= DICT_MAX_SAMPLES {
+            break;
+        }
+    }
+
+    if samples.len() < DICT_MIN_SAMPLES {
+        return None;
+    }
+
+    zstd::dict::from_samples(&samples, DICT_SIZE).ok()
+}
+
+/// Build the outer writer that the `tar::Builder` streams into. `reproducible` zeroes
+/// the gzip header's embedded mtime. For `Format::TarZst`, `dict` (if present) primes
+/// the encoder's compression context; its bytes are stored as a small length-prefixed
+/// preamble ahead of the compressed stream so `make_decoder` can recover the exact same
+/// dictionary without needing it shipped as a (circularly self-referential) tar member.
+fn make_encoder(
+    output: File,
+    format: Format,
+    reproducible: bool,
+    dict: Option<&[u8]>,
+) -> io::Result<Box<dyn Write>> {
+    Ok(match format {
+        Format::TarZst => {
+            let mut output = output;
+            let dict_bytes = dict.unwrap_or(&[]);
+            output.write_all(&(dict_bytes.len() as u32).to_le_bytes())?;
+            output.write_all(dict_bytes)?;
+
+            let mut encoder = match dict {
+                Some(d) => Encoder::with_dictionary(output, 21, d)?,
+                None => Encoder::new(output, 21)?,
             };
-            buffer.extend_from_slice(&chunk[..n]);
-            global_pb.inc(n as u64);
-        }
-
-        // Compress
-        let compressed = match zstd::encode_all(&buffer[..], 21) {
-            Ok(c) => c,
-            Err(_) => {
-                results.lock().unwrap().push(FileData {
-                    rel_path,
-                    compressed: vec![],
-                    header: Header::new_gnu(),
-                    success: false,
-                });
-                return;
+            if let Ok(n) = std::thread::available_parallelism() {
+                let _ = encoder.multithread(n.get() as u32);
             }
-        };
+            Box::new(BufWriter::new(encoder.auto_finish()))
+        }
+        Format::TarGz => {
+            let mtime = if reproducible { 0 } else { current_unix_time() };
+            Box::new(BufWriter::new(
+                flate2::GzBuilder::new()
+                    .mtime(mtime)
+                    .write(output, Compression::best()),
+            ))
+        }
+        Format::TarXz => Box::new(BufWriter::new(XzEncoder::new(output, 9))),
+        Format::Zip => unreachable!("zip archives are written directly, not through the tar path"),
+    })
+}
 
-        let mut header = Header::new_gnu();
-        if let Err(_) = header.set_path(&rel_path) {
-            results.lock().unwrap().push(FileData {
-                rel_path,
-                compressed,
-                header: Header::new_gnu(),
-                success: false,
-            });
-            return;
-        }
-        header.set_size(compressed.len() as u64);
-        header.set_cksum();
+fn current_unix_time() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as u32)
+        .unwrap_or(0)
+}
 
-        results.lock().unwrap().push(FileData {
-            rel_path,
-            compressed,
-            header,
-            success: true,
-        });
-    });
+/// Build the reader that an `Archive` unpacks from. For `Format::TarZst`, reads the
+/// dictionary preamble written by `make_encoder` first, then feeds it to the decoder so
+/// it matches the compressor's context.
+fn make_decoder(mut input: File, format: Format) -> io::Result<Box<dyn Read>> {
+    Ok(match format {
+        Format::TarZst => {
+            let mut len_bytes = [0u8; 4];
+            input.read_exact(&mut len_bytes)?;
+            let dict_len = u32::from_le_bytes(len_bytes) as usize;
+            let mut dict = vec![0u8; dict_len];
+            input.read_exact(&mut dict)?;
 
-    global_pb.finish_with_message("📦 Сжатие завершено");
+            if dict.is_empty() {
+                Box::new(zstd::stream::Decoder::new(BufReader::new(input))?)
+            } else {
+                Box::new(zstd::stream::Decoder::with_dictionary(BufReader::new(input), &dict)?)
+            }
+        }
+        Format::TarGz => Box::new(GzDecoder::new(BufReader::new(input))),
+        Format::TarXz => Box::new(XzDecoder::new(BufReader::new(input))),
+        Format::Zip => unreachable!("zip archives are read directly, not through the tar path"),
+    })
+}
+
+fn compress_folder_zip(input_folder: &str, output_file: &str) -> io::Result<()> {
+    let entries: Vec<_> = WalkDir::new(input_folder)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect();
 
-    // Сохраняем архив
     let output = File::create(output_file)?;
-    let writer = BufWriter::new(Encoder::new(output, 21)?.auto_finish());
-    let mut tar = Builder::new(writer);
+    let mut zip = ZipWriter::new(BufWriter::new(output));
 
-    for file in results.lock().unwrap().iter().filter(|f| f.success) {
-        tar.append(&file.header, &file.compressed[..])?;
-    }
+    for entry in &entries {
+        let path = entry.path();
+        let rel_path = path.strip_prefix(input_folder).unwrap();
+        let rel_name = rel_path.to_string_lossy();
+        let metadata = fs::symlink_metadata(path)?;
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Zstd)
+            .unix_permissions(metadata.mode())
+            .last_modified_time(unix_time_to_zip_datetime(metadata.mtime()));
 
-    println!("\n📃 Результаты:");
-    for file in results.lock().unwrap().iter() {
-        println!(
-            "{:<60} [{}]",
-            file.rel_path.display(),
-            if file.success { "OK" } else { "ERR" }
-        );
+        let file_type = metadata.file_type();
+        if file_type.is_symlink() {
+            let target = fs::read_link(path)?;
+            zip.add_symlink(rel_name, target.to_string_lossy(), options)?;
+        } else if file_type.is_dir() {
+            zip.add_directory(rel_name, options)?;
+        } else {
+            zip.start_file(rel_name, options)?;
+            let mut file = File::open(path)?;
+            io::copy(&mut file, &mut zip)?;
+        }
     }
 
+    zip.finish()?;
     println!("\n✅ Папка '{}' сжата в '{}'", input_folder, output_file);
     Ok(())
 }
 
+/// Converts a unix timestamp into the zip format's DOS-style date/time. Dates outside the
+/// format's 1980-2107 range are not clamped; they fall back to zip's default datetime.
+fn unix_time_to_zip_datetime(secs: i64) -> zip::DateTime {
+    let days = secs.div_euclid(86400);
+    let secs_of_day = secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let hour = (secs_of_day / 3600) as u8;
+    let minute = ((secs_of_day % 3600) / 60) as u8;
+    let second = (secs_of_day % 60) as u8;
+
+    zip::DateTime::from_date_and_time(year as u16, month as u8, day as u8, hour, minute, second)
+        .unwrap_or_default()
+}
+
+/// Howard Hinnant's `civil_from_days`: turns a day count since the Unix epoch into a
+/// (year, month, day) civil date, used to fill in the zip entry timestamp above.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = (z - era * 146_097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = if m <= 2 { y + 1 } else { y };
+    (y, m, d)
+}
+
 fn decompress_folder(input_file: &str, output_folder: &str) -> io::Result<()> {
+    let format = Format::from_path(input_file);
+    fs::create_dir_all(output_folder)?;
+
+    if format == Format::Zip {
+        let file = File::open(input_file)?;
+        let mut archive = ZipArchive::new(file)?;
+        archive.extract(output_folder)?;
+        println!("✅ Архив '{}' распакован в '{}'", input_file, output_folder);
+        return Ok(());
+    }
+
     let file = File::open(input_file)?;
-    let decoder = zstd::stream::Decoder::new(BufReader::new(file))?;
+    let decoder = make_decoder(file, format)?;
     let mut archive = Archive::new(decoder);
 
-    fs::create_dir_all(output_folder)?;
-    archive.unpack(output_folder)?;
+    if format == Format::TarZst {
+        decompress_tar_zst(&mut archive, output_folder)?;
+    } else {
+        archive.unpack(output_folder)?;
+    }
+
     println!("✅ Архив '{}' распакован в '{}'", input_file, output_folder);
     Ok(())
 }
+
+/// Mirrors `append_entry`'s TarZst encoding: directories and symlinks are restored
+/// directly from their headers, `.chunks/<id>` entries are collected into a table, and
+/// every other regular-file entry is a manifest of chunk ids that gets reassembled by
+/// concatenating the referenced chunks in order.
+fn decompress_tar_zst<R: Read>(archive: &mut Archive<R>, output_folder: &str) -> io::Result<()> {
+    let mut chunks: HashMap<ChunkId, Vec<u8>> = HashMap::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let dest = Path::new(output_folder).join(&path);
+
+        match entry.header().entry_type() {
+            EntryType::Directory => {
+                fs::create_dir_all(&dest)?;
+            }
+            EntryType::Symlink => {
+                let target = entry.link_name()?.unwrap_or_default().into_owned();
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let _ = fs::remove_file(&dest);
+                std::os::unix::fs::symlink(&target, &dest)?;
+            }
+            _ if path.starts_with(CHUNKS_DIR) => {
+                let hex_id = path
+                    .strip_prefix(CHUNKS_DIR)
+                    .unwrap()
+                    .to_string_lossy()
+                    .trim_start_matches('/')
+                    .to_string();
+                let id = parse_chunk_id(&hex_id)?;
+                let mut bytes = Vec::with_capacity(entry.header().size()? as usize);
+                entry.read_to_end(&mut bytes)?;
+                chunks.insert(id, bytes);
+            }
+            _ => {
+                let mut manifest = Vec::new();
+                entry.read_to_end(&mut manifest)?;
+
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+
+                let mut out = File::create(&dest)?;
+                for id_bytes in manifest.chunks_exact(32) {
+                    let mut id = ChunkId::default();
+                    id.copy_from_slice(id_bytes);
+                    let bytes = chunks.get(&id).ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidData, "missing chunk referenced by manifest")
+                    })?;
+                    out.write_all(bytes)?;
+                }
+
+                fs::set_permissions(&dest, fs::Permissions::from_mode(entry.header().mode()?))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn list_archive(input_file: &str) -> io::Result<()> {
+    match Format::from_path(input_file) {
+        Format::Zip => list_archive_zip(input_file),
+        Format::TarZst => list_archive_tar_zst(input_file),
+        format => list_archive_tar(input_file, format),
+    }
+}
+
+fn list_archive_tar(input_file: &str, format: Format) -> io::Result<()> {
+    let file = File::open(input_file)?;
+    let decoder = make_decoder(file, format)?;
+    let mut archive = Archive::new(decoder);
+
+    println!("{:<60} {:<6} {:>12}", "PATH", "TYPE", "SIZE");
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        let entry_type = match entry.header().entry_type() {
+            EntryType::Directory => "dir",
+            EntryType::Symlink => "link",
+            _ => "file",
+        };
+        let size = entry.header().size()?;
+        println!("{:<60} {:<6} {:>12}", path.display(), entry_type, size);
+    }
+
+    Ok(())
+}
+
+/// TarZst regular-file entries are manifests of chunk ids (see `append_entry`), not raw
+/// content, so listing them needs to fold each manifest's chunks back into the file's
+/// real size instead of printing the (much smaller) manifest byte length, and fold the
+/// `.chunks/<id>` bookkeeping entries into that total rather than printing them as if
+/// they were archive members in their own right.
+fn list_archive_tar_zst(input_file: &str) -> io::Result<()> {
+    let file = File::open(input_file)?;
+    let decoder = make_decoder(file, Format::TarZst)?;
+    let mut archive = Archive::new(decoder);
+
+    let mut chunk_sizes: HashMap<ChunkId, u64> = HashMap::new();
+
+    println!("{:<60} {:<6} {:>12}", "PATH", "TYPE", "SIZE");
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+
+        match entry.header().entry_type() {
+            EntryType::Directory => {
+                println!("{:<60} {:<6} {:>12}", path.display(), "dir", 0);
+            }
+            EntryType::Symlink => {
+                println!("{:<60} {:<6} {:>12}", path.display(), "link", 0);
+            }
+            _ if path.starts_with(CHUNKS_DIR) => {
+                let hex_id = path
+                    .strip_prefix(CHUNKS_DIR)
+                    .unwrap()
+                    .to_string_lossy()
+                    .trim_start_matches('/')
+                    .to_string();
+                if let Ok(id) = parse_chunk_id(&hex_id) {
+                    chunk_sizes.insert(id, entry.header().size()?);
+                }
+            }
+            _ => {
+                let mut manifest = Vec::new();
+                entry.read_to_end(&mut manifest)?;
+
+                let real_size: u64 = manifest
+                    .chunks_exact(32)
+                    .map(|id_bytes| {
+                        let mut id = ChunkId::default();
+                        id.copy_from_slice(id_bytes);
+                        chunk_sizes.get(&id).copied().unwrap_or(0)
+                    })
+                    .sum();
+
+                println!("{:<60} {:<6} {:>12}", path.display(), "file", real_size);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `.zip` isn't a tar stream, so it gets its own listing path through `ZipArchive`
+/// instead of `make_decoder`/`Archive`.
+fn list_archive_zip(input_file: &str) -> io::Result<()> {
+    let file = File::open(input_file)?;
+    let mut archive = ZipArchive::new(file)?;
+
+    println!("{:<60} {:<6} {:>12}", "PATH", "TYPE", "SIZE");
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        let entry_type = if entry.is_dir() { "dir" } else { "file" };
+        println!("{:<60} {:<6} {:>12}", entry.name(), entry_type, entry.size());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_stream_reassembles_to_the_original_bytes() {
+        let data: Vec<u8> = (0..500_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut reassembled = Vec::new();
+        let mut chunk_count = 0;
+        chunk_stream(data.as_slice(), |bytes| {
+            assert!(bytes.len() <= CHUNK_MAX_SIZE);
+            reassembled.extend_from_slice(bytes);
+            chunk_count += 1;
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(reassembled, data);
+        assert!(chunk_count > 1, "expected more than one chunk for 500KB of input");
+    }
+
+    #[test]
+    fn chunk_stream_is_deterministic_for_the_same_input() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i * 7 % 253) as u8).collect();
+
+        let chunk_lens = |input: &[u8]| {
+            let mut lens = Vec::new();
+            chunk_stream(input, |bytes| {
+                lens.push(bytes.len());
+                Ok(())
+            })
+            .unwrap();
+            lens
+        };
+
+        assert_eq!(chunk_lens(&data), chunk_lens(&data));
+    }
+
+    #[test]
+    fn chunk_id_hex_round_trips() {
+        let id: ChunkId = *blake3::hash(b"hello world").as_bytes();
+        let hex = to_hex(&id);
+        assert_eq!(parse_chunk_id(&hex).unwrap(), id);
+    }
+
+    #[test]
+    fn parse_chunk_id_rejects_malformed_input() {
+        assert!(parse_chunk_id("not-a-valid-hex-id").is_err());
+        assert!(parse_chunk_id("ab").is_err());
+    }
+
+    #[test]
+    fn civil_from_days_matches_known_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_692), (2023, 12, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+}